@@ -1,26 +1,99 @@
 use anyhow::{Context, Result};
 use clap::Parser;
+use ::image as image_crate;
 use printpdf::*;
 use std::fs::File;
 use std::io::BufWriter;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-// Grid layout constants
-const GRID_COLS: usize = 4;
-const GRID_ROWS: usize = 4;
-const CARDS_PER_PAGE: usize = GRID_COLS * GRID_ROWS;
+// Text positioning
+const TEXT_MARGIN_MM: f32 = 10.0;
 
-// A4 dimensions in mm
-const A4_WIDTH_MM: f32 = 210.0;
-const A4_HEIGHT_MM: f32 = 297.0;
-const MARGIN_MM: f32 = 5.0;
+// A named page size, or a custom one given as width x height in mm.
+#[derive(Debug, Clone, Copy)]
+enum PageSize {
+    A4,
+    Letter,
+    Custom(f32, f32),
+}
 
-// Calculate card dimensions
-const CARD_WIDTH_MM: f32 = (A4_WIDTH_MM - 2.0 * MARGIN_MM) / GRID_COLS as f32;
-const CARD_HEIGHT_MM: f32 = (A4_HEIGHT_MM - 2.0 * MARGIN_MM) / GRID_ROWS as f32;
+impl PageSize {
+    fn dimensions_mm(&self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Custom(width, height) => (*width, *height),
+        }
+    }
+}
 
-// Text positioning
-const TEXT_MARGIN_MM: f32 = 10.0;
+impl std::str::FromStr for PageSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "a4" => Ok(PageSize::A4),
+            "letter" => Ok(PageSize::Letter),
+            other => {
+                let dims = other.strip_prefix("custom:").ok_or_else(|| {
+                    format!("Unknown page size '{other}' (expected a4, letter, or custom:WxH)")
+                })?;
+                let (width, height) = dims.split_once('x').ok_or_else(|| {
+                    format!("Invalid custom page size '{dims}' (expected WxH, e.g. custom:148x210)")
+                })?;
+                let width: f32 = width
+                    .parse()
+                    .map_err(|_| format!("Invalid page width: '{width}'"))?;
+                let height: f32 = height
+                    .parse()
+                    .map_err(|_| format!("Invalid page height: '{height}'"))?;
+                Ok(PageSize::Custom(width, height))
+            }
+        }
+    }
+}
+
+// The resolved sheet geometry: grid dimensions and page size turned into per-card mm sizes.
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    cols: usize,
+    rows: usize,
+    page_width_mm: f32,
+    page_height_mm: f32,
+    margin_mm: f32,
+    card_width_mm: f32,
+    card_height_mm: f32,
+}
+
+impl Layout {
+    fn new(page: PageSize, cols: usize, rows: usize, margin_mm: f32) -> Result<Self> {
+        anyhow::ensure!(cols >= 1, "--cols must be at least 1, got {cols}");
+        anyhow::ensure!(rows >= 1, "--rows must be at least 1, got {rows}");
+
+        let (page_width_mm, page_height_mm) = page.dimensions_mm();
+        let card_width_mm = (page_width_mm - 2.0 * margin_mm) / cols as f32;
+        let card_height_mm = (page_height_mm - 2.0 * margin_mm) / rows as f32;
+
+        anyhow::ensure!(
+            card_width_mm > 0.0 && card_height_mm > 0.0,
+            "page size {page_width_mm}x{page_height_mm}mm with margin {margin_mm}mm and a {cols}x{rows} grid leaves no room for cards"
+        );
+
+        Ok(Layout {
+            cols,
+            rows,
+            page_width_mm,
+            page_height_mm,
+            margin_mm,
+            card_width_mm,
+            card_height_mm,
+        })
+    }
+
+    fn cards_per_page(&self) -> usize {
+        self.cols * self.rows
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Generate flash cards PDF from CSV", long_about = None)]
@@ -32,84 +105,268 @@ struct Args {
     /// Output PDF file
     #[arg(short, long)]
     output: PathBuf,
+
+    /// TrueType/OpenType font file to embed for full Unicode support (accented, Greek,
+    /// Cyrillic, CJK, ...). Falls back to the built-in Helvetica Bold (WinAnsi only) when absent.
+    #[arg(long)]
+    font: Option<PathBuf>,
+
+    /// Largest font size (in points) to try before shrinking text to fit the card
+    #[arg(long, default_value_t = 18.0)]
+    max_font_size: f32,
+
+    /// Smallest font size (in points) to shrink to; text below this may still overflow
+    #[arg(long, default_value_t = 8.0)]
+    min_font_size: f32,
+
+    /// Page size: a4, letter, or custom:<width>x<height> in mm
+    #[arg(long, default_value = "a4")]
+    page: PageSize,
+
+    /// Number of card columns per sheet
+    #[arg(long, default_value_t = 4)]
+    cols: usize,
+
+    /// Number of card rows per sheet
+    #[arg(long, default_value_t = 4)]
+    rows: usize,
+
+    /// Page margin in mm
+    #[arg(long, default_value_t = 5.0)]
+    margin: f32,
+
+    /// Treat the CSV as headered, with named columns (title, body, category, footer) instead
+    /// of two pipe-delimited sides. Detected automatically when the first row's cells look
+    /// like these column names; pass this to force it.
+    #[arg(long)]
+    has_headers: bool,
+}
+
+// A card side is plain text by default, or a `qr:<text>`/`img:<path>` CSV cell asking for a
+// scannable QR code or an embedded picture instead.
+#[derive(Debug, Clone)]
+enum CardSide {
+    Text(String),
+    Qr(String),
+    Image(PathBuf),
+}
+
+impl CardSide {
+    fn parse(raw: &str) -> Self {
+        if let Some(data) = raw.strip_prefix("qr:") {
+            CardSide::Qr(data.to_string())
+        } else if let Some(path) = raw.strip_prefix("img:") {
+            CardSide::Image(PathBuf::from(path))
+        } else {
+            CardSide::Text(raw.to_string())
+        }
+    }
 }
 
+// Named columns a headered CSV can map onto a structured card.
+const STRUCTURED_COLUMNS: [&str; 4] = ["title", "body", "category", "footer"];
+
 #[derive(Debug, Clone)]
-struct FlashCard {
-    side_a: String,
-    side_b: String,
+enum FlashCard {
+    /// The original two-sided Q/A card, one side shown per page.
+    TwoSided { side_a: CardSide, side_b: CardSide },
+    /// A headered-CSV card with named, optional fields, shown the same on every page.
+    Structured {
+        title: Option<String>,
+        body: Option<String>,
+        category: Option<String>,
+        footer: Option<String>,
+    },
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
     // Read and parse CSV
-    let cards = read_csv(&args.input)?;
+    let cards = read_csv(&args.input, args.has_headers)?;
     println!("Loaded {} flash cards from CSV", cards.len());
 
     // Generate PDF
-    generate_pdf(&cards, &args.output)?;
+    let layout = Layout::new(args.page, args.cols, args.rows, args.margin)?;
+    generate_pdf(
+        &cards,
+        &args.output,
+        args.font.as_deref(),
+        args.max_font_size,
+        args.min_font_size,
+        &layout,
+    )?;
     println!("Generated PDF: {}", args.output.display());
 
     Ok(())
 }
 
-fn read_csv(path: &PathBuf) -> Result<Vec<FlashCard>> {
+fn read_csv(path: &PathBuf, force_headers: bool) -> Result<Vec<FlashCard>> {
     let mut reader = csv::ReaderBuilder::new()
         .delimiter(b'|')
         .has_headers(false)
         .from_path(path)
         .context("Failed to open CSV file")?;
 
+    let mut records = reader.records();
+
+    let first_record = match records.next() {
+        Some(result) => result.context("Failed to read CSV record")?,
+        None => return Ok(Vec::new()),
+    };
+
+    let columns = if force_headers || looks_like_header(&first_record) {
+        Some(parse_header(&first_record))
+    } else {
+        None
+    };
+
     let mut cards = Vec::new();
 
-    for result in reader.records() {
+    if columns.is_none() {
+        cards.push(parse_two_sided(&first_record)?);
+    }
+
+    for result in records {
         let record = result.context("Failed to read CSV record")?;
+        cards.push(match &columns {
+            Some(columns) => parse_structured(&record, columns),
+            None => parse_two_sided(&record)?,
+        });
+    }
+
+    Ok(cards)
+}
 
-        if record.len() < 2 {
-            anyhow::bail!("CSV record must have at least 2 columns");
+// A row is treated as a header only if every non-empty cell names one of the structured
+// columns and at least two columns are recognized. Requiring a single recognized cell would
+// misclassify an ordinary two-sided data row (e.g. "category|categorie") as a header and
+// silently drop it; `--has-headers` remains the explicit override for ambiguous cases.
+fn looks_like_header(record: &csv::StringRecord) -> bool {
+    let mut recognized = 0;
+    for field in record.iter() {
+        let field = field.trim();
+        if field.is_empty() {
+            continue;
+        }
+        if !STRUCTURED_COLUMNS.contains(&field.to_lowercase().as_str()) {
+            return false;
         }
+        recognized += 1;
+    }
+    recognized >= 2
+}
 
-        cards.push(FlashCard {
-            side_a: record[0].to_string(),
-            side_b: record[1].to_string(),
-        });
+fn parse_header(record: &csv::StringRecord) -> Vec<String> {
+    record.iter().map(|field| field.trim().to_lowercase()).collect()
+}
+
+fn parse_two_sided(record: &csv::StringRecord) -> Result<FlashCard> {
+    if record.len() < 2 {
+        anyhow::bail!("CSV record must have at least 2 columns");
     }
 
-    Ok(cards)
+    Ok(FlashCard::TwoSided {
+        side_a: CardSide::parse(&record[0]),
+        side_b: CardSide::parse(&record[1]),
+    })
+}
+
+fn parse_structured(record: &csv::StringRecord, columns: &[String]) -> FlashCard {
+    let mut title = None;
+    let mut body = None;
+    let mut category = None;
+    let mut footer = None;
+
+    for (column, value) in columns.iter().zip(record.iter()) {
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+
+        match column.as_str() {
+            "title" => title = Some(value.to_string()),
+            "body" => body = Some(value.to_string()),
+            "category" => category = Some(value.to_string()),
+            "footer" => footer = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    FlashCard::Structured { title, body, category, footer }
 }
 
-fn generate_pdf(cards: &[FlashCard], output_path: &PathBuf) -> Result<()> {
+fn generate_pdf(
+    cards: &[FlashCard],
+    output_path: &PathBuf,
+    font_path: Option<&Path>,
+    max_font_size: f32,
+    min_font_size: f32,
+    layout: &Layout,
+) -> Result<()> {
+    let cards_per_page = layout.cards_per_page();
+
     // Calculate total pages needed (2 pages per sheet: front + back)
-    let total_sheets = (cards.len() + CARDS_PER_PAGE - 1) / CARDS_PER_PAGE;
+    let total_sheets = cards.len().div_ceil(cards_per_page);
     let total_pages = total_sheets * 2; // front and back
 
     // Ensure even number of pages (pairs of sheets)
-    let total_pages = if total_pages % 2 == 0 {
+    let total_pages = if total_pages.is_multiple_of(2) {
         total_pages
     } else {
         total_pages + 2 // Add one more sheet (front + back)
     };
 
-    let (doc, page1, layer1) =
-        PdfDocument::new("Flash Cards", Mm(A4_WIDTH_MM), Mm(A4_HEIGHT_MM), "Layer 1");
+    let (doc, page1, layer1) = PdfDocument::new(
+        "Flash Cards",
+        Mm(layout.page_width_mm),
+        Mm(layout.page_height_mm),
+        "Layer 1",
+    );
 
-    // Load a built-in bold font
-    let font = doc.add_builtin_font(BuiltinFont::HelveticaBold)?;
+    // Load the requested font. Embedded TrueType/OpenType fonts are written by printpdf
+    // as Identity-H CID fonts with an accompanying ToUnicode CMap, so full Unicode card
+    // text (accented vocabulary, Greek, Cyrillic, CJK, ...) stays both renderable and
+    // copyable. Without `--font`, fall back to the built-in Helvetica Bold (WinAnsi only).
+    //
+    // `--font` only accepts a single file, so there's no separate regular-weight face to
+    // load for it; the same file is embedded a second time and used as the regular face. Without
+    // `--font`, builtin Helvetica and Helvetica-Bold give structured cards real contrast
+    // between the title and the body/footer.
+    let (font, regular_font, metrics) = match font_path {
+        Some(path) => {
+            let bytes = std::fs::read(path)
+                .with_context(|| format!("Failed to read font file: {}", path.display()))?;
+            let font = doc
+                .add_external_font(bytes.as_slice())
+                .with_context(|| format!("Failed to embed font: {}", path.display()))?;
+            let regular_font = doc
+                .add_external_font(bytes.as_slice())
+                .with_context(|| format!("Failed to embed font: {}", path.display()))?;
+            (font, regular_font, FontMetrics::Embedded(bytes))
+        }
+        None => (
+            doc.add_builtin_font(BuiltinFont::HelveticaBold)?,
+            doc.add_builtin_font(BuiltinFont::Helvetica)?,
+            FontMetrics::Builtin,
+        ),
+    };
+    let resolved_metrics = metrics.resolve()?;
 
     let mut current_layer = layer1;
     let mut current_page = page1;
 
-    // Process cards in chunks of CARDS_PER_PAGE
+    // Process cards in chunks of cards_per_page
     for sheet_idx in 0..(total_pages / 2) {
-        let start_idx = sheet_idx * CARDS_PER_PAGE;
-        let end_idx = (start_idx + CARDS_PER_PAGE).min(cards.len());
+        let start_idx = sheet_idx * cards_per_page;
+        let end_idx = (start_idx + cards_per_page).min(cards.len());
         let sheet_cards: Vec<Option<&FlashCard>> =
             (start_idx..end_idx).map(|i| cards.get(i)).collect();
 
         // Create front page (side A)
         if sheet_idx > 0 {
-            let (page, layer) = doc.add_page(Mm(A4_WIDTH_MM), Mm(A4_HEIGHT_MM), "Layer 1");
+            let (page, layer) =
+                doc.add_page(Mm(layout.page_width_mm), Mm(layout.page_height_mm), "Layer 1");
             current_page = page;
             current_layer = layer;
         }
@@ -119,15 +376,18 @@ fn generate_pdf(cards: &[FlashCard], output_path: &PathBuf) -> Result<()> {
             current_layer,
             current_page,
             &font,
+            &regular_font,
+            &resolved_metrics,
             &sheet_cards,
             true, // front side (A)
-            CARD_WIDTH_MM,
-            CARD_HEIGHT_MM,
-            MARGIN_MM,
-        );
+            max_font_size,
+            min_font_size,
+            layout,
+        )?;
 
         // Create back page (side B)
-        let (page, layer) = doc.add_page(Mm(A4_WIDTH_MM), Mm(A4_HEIGHT_MM), "Layer 1");
+        let (page, layer) =
+            doc.add_page(Mm(layout.page_width_mm), Mm(layout.page_height_mm), "Layer 1");
         current_page = page;
         current_layer = layer;
 
@@ -136,12 +396,14 @@ fn generate_pdf(cards: &[FlashCard], output_path: &PathBuf) -> Result<()> {
             current_layer,
             current_page,
             &font,
+            &regular_font,
+            &resolved_metrics,
             &sheet_cards,
             false, // back side (B)
-            CARD_WIDTH_MM,
-            CARD_HEIGHT_MM,
-            MARGIN_MM,
-        );
+            max_font_size,
+            min_font_size,
+            layout,
+        )?;
     }
 
     // Save the PDF
@@ -152,82 +414,353 @@ fn generate_pdf(cards: &[FlashCard], output_path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-// Wrap text to fit within available space
-fn wrap_text(text: &str, font_size: f32, available_height_mm: f32) -> Vec<String> {
-    // Approximate character width for Helvetica Bold (pt to mm: 1pt ≈ 0.3528mm)
-    // Using 0.5 instead of 0.6 to allow more characters per line
-    let avg_char_width_mm = font_size * 0.5 * 0.3528;
+// Per-glyph advance widths, in font units (1000 units/em for the builtin AFM table, or
+// whatever `head.unitsPerEm` reports for an embedded TTF/OTF).
+enum FontMetrics {
+    /// Helvetica-Bold, measured from its AFM width table.
+    Builtin,
+    /// An embedded TrueType/OpenType font, measured from its `hmtx`/`head` tables.
+    Embedded(Vec<u8>),
+}
+
+impl FontMetrics {
+    // Parse the embedded font's `Face` once per document. Every glyph-width lookup during
+    // layout (per char, per word, per font-size step `fit_text` tries, per card) then borrows
+    // this instead of re-parsing the whole font file from scratch each time.
+    fn resolve(&self) -> Result<ResolvedMetrics<'_>> {
+        match self {
+            FontMetrics::Builtin => Ok(ResolvedMetrics::Builtin),
+            FontMetrics::Embedded(bytes) => {
+                let face = ttf_parser::Face::parse(bytes, 0).context("Failed to parse embedded font")?;
+                Ok(ResolvedMetrics::Embedded(Box::new(face)))
+            }
+        }
+    }
+}
+
+enum ResolvedMetrics<'a> {
+    Builtin,
+    Embedded(Box<ttf_parser::Face<'a>>),
+}
+
+impl ResolvedMetrics<'_> {
+    fn units_per_em(&self) -> u16 {
+        match self {
+            ResolvedMetrics::Builtin => 1000,
+            ResolvedMetrics::Embedded(face) => face.units_per_em(),
+        }
+    }
+
+    fn char_advance(&self, c: char) -> u16 {
+        match self {
+            ResolvedMetrics::Builtin => helvetica_bold_advance(c),
+            ResolvedMetrics::Embedded(face) => face
+                .glyph_index(c)
+                .and_then(|gid| face.glyph_hor_advance(gid))
+                .unwrap_or_else(|| self.units_per_em() / 2),
+        }
+    }
 
-    // Calculate max characters per line
-    let max_chars = (available_height_mm / avg_char_width_mm) as usize;
+    fn text_width_mm(&self, text: &str, font_size: f32) -> f32 {
+        let width_units: u32 = text.chars().map(|c| self.char_advance(c) as u32).sum();
+        width_units as f32 / self.units_per_em() as f32 * font_size * 0.3528
+    }
+}
 
-    if text.len() <= max_chars {
-        return vec![text.to_string()];
+// AFM advance widths for Helvetica-Bold (1000 units/em), covering printable ASCII.
+// Any other character (WinAnsi's accented Latin-1 letters included) gets the fallback width.
+fn helvetica_bold_advance(c: char) -> u16 {
+    match c {
+        ' ' => 278,
+        '!' => 333,
+        '"' => 474,
+        '#' => 556,
+        '$' => 556,
+        '%' => 889,
+        '&' => 722,
+        '\'' => 238,
+        '(' => 333,
+        ')' => 333,
+        '*' => 389,
+        '+' => 584,
+        ',' => 278,
+        '-' => 333,
+        '.' => 278,
+        '/' => 278,
+        '0'..='9' => 556,
+        ':' => 333,
+        ';' => 333,
+        '<' => 584,
+        '=' => 584,
+        '>' => 584,
+        '?' => 611,
+        '@' => 975,
+        'A' => 722,
+        'B' => 722,
+        'C' => 722,
+        'D' => 722,
+        'E' => 667,
+        'F' => 611,
+        'G' => 778,
+        'H' => 722,
+        'I' => 278,
+        'J' => 556,
+        'K' => 722,
+        'L' => 611,
+        'M' => 833,
+        'N' => 722,
+        'O' => 778,
+        'P' => 667,
+        'Q' => 778,
+        'R' => 722,
+        'S' => 667,
+        'T' => 611,
+        'U' => 722,
+        'V' => 667,
+        'W' => 944,
+        'X' => 667,
+        'Y' => 667,
+        'Z' => 611,
+        '[' => 333,
+        '\\' => 278,
+        ']' => 333,
+        '^' => 584,
+        '_' => 556,
+        '`' => 333,
+        'a' => 556,
+        'b' => 611,
+        'c' => 556,
+        'd' => 611,
+        'e' => 556,
+        'f' => 333,
+        'g' => 611,
+        'h' => 611,
+        'i' => 278,
+        'j' => 278,
+        'k' => 556,
+        'l' => 278,
+        'm' => 889,
+        'n' => 611,
+        'o' => 611,
+        'p' => 611,
+        'q' => 611,
+        'r' => 389,
+        's' => 556,
+        't' => 333,
+        'u' => 611,
+        'v' => 556,
+        'w' => 778,
+        'x' => 556,
+        'y' => 556,
+        'z' => 500,
+        '{' => 389,
+        '|' => 280,
+        '}' => 389,
+        '~' => 584,
+        _ => 556, // unmapped glyph: fall back to the average digit width
     }
+}
 
-    // Split text into words and wrap
+// Wrap text to fit within `available_width_mm`, measuring real glyph advances rather than
+// assuming a fixed character width. Returns the wrapped lines alongside each line's measured
+// width in mm, so the caller can center them precisely. A single word wider than the available
+// width is hard-broken mid-word rather than left to overflow the card border.
+fn wrap_text(text: &str, font_size: f32, available_width_mm: f32, metrics: &ResolvedMetrics) -> (Vec<String>, Vec<f32>) {
+    let space_width_mm = metrics.text_width_mm(" ", font_size);
     let words: Vec<&str> = text.split_whitespace().collect();
+
     let mut lines = Vec::new();
+    let mut widths = Vec::new();
     let mut current_line = String::new();
+    let mut current_width_mm = 0.0;
 
     for word in words {
-        let test_line = if current_line.is_empty() {
-            word.to_string()
-        } else {
-            format!("{} {}", current_line, word)
-        };
+        let word_width_mm = metrics.text_width_mm(word, font_size);
+
+        if word_width_mm > available_width_mm {
+            if !current_line.is_empty() {
+                lines.push(std::mem::take(&mut current_line));
+                widths.push(current_width_mm);
+                current_width_mm = 0.0;
+            }
+            let (broken_lines, broken_widths) =
+                hard_break_word(word, font_size, available_width_mm, metrics);
+            lines.extend(broken_lines);
+            widths.extend(broken_widths);
+            continue;
+        }
 
-        if test_line.len() <= max_chars {
-            current_line = test_line;
+        let extra_mm = if current_line.is_empty() { 0.0 } else { space_width_mm };
+        if current_width_mm + extra_mm + word_width_mm <= available_width_mm {
+            if !current_line.is_empty() {
+                current_line.push(' ');
+            }
+            current_line.push_str(word);
+            current_width_mm += extra_mm + word_width_mm;
         } else {
             if !current_line.is_empty() {
-                lines.push(current_line);
+                lines.push(std::mem::take(&mut current_line));
+                widths.push(current_width_mm);
             }
             current_line = word.to_string();
+            current_width_mm = word_width_mm;
         }
     }
 
     if !current_line.is_empty() {
         lines.push(current_line);
+        widths.push(current_width_mm);
     }
 
     if lines.is_empty() {
-        vec![text.to_string()]
-    } else {
-        lines
+        let width_mm = metrics.text_width_mm(text, font_size);
+        lines.push(text.to_string());
+        widths.push(width_mm);
     }
+
+    (lines, widths)
 }
 
+// Break a single word that doesn't fit on a line of its own into as many pieces as needed.
+fn hard_break_word(
+    word: &str,
+    font_size: f32,
+    available_width_mm: f32,
+    metrics: &ResolvedMetrics,
+) -> (Vec<String>, Vec<f32>) {
+    let mut lines = Vec::new();
+    let mut widths = Vec::new();
+    let mut current = String::new();
+    let mut current_width_mm = 0.0;
+
+    for c in word.chars() {
+        let char_width_mm = metrics.text_width_mm(&c.to_string(), font_size);
+        if !current.is_empty() && current_width_mm + char_width_mm > available_width_mm {
+            lines.push(std::mem::take(&mut current));
+            widths.push(current_width_mm);
+            current_width_mm = 0.0;
+        }
+        current.push(c);
+        current_width_mm += char_width_mm;
+    }
+
+    if !current.is_empty() {
+        lines.push(current);
+        widths.push(current_width_mm);
+    }
+
+    (lines, widths)
+}
+
+// Find the largest font size in `min_font_size..=max_font_size` (in 1pt steps) at which the
+// wrapped text block fits within the available length (line-length axis) and stack width
+// (axis the wrapped lines stack along). Falls back to `min_font_size` if nothing fits.
+fn fit_text(
+    text: &str,
+    max_font_size: f32,
+    min_font_size: f32,
+    available_length_mm: f32,
+    available_stack_mm: f32,
+    metrics: &ResolvedMetrics,
+) -> (f32, Vec<String>, Vec<f32>) {
+    let mut font_size = max_font_size;
+
+    loop {
+        let (lines, widths) = wrap_text(text, font_size, available_length_mm, metrics);
+        let line_spacing_mm = font_size * 0.3528 * 1.1;
+        let block_width_mm = lines.len() as f32 * line_spacing_mm;
+        let longest_line_mm = widths.iter().cloned().fold(0.0_f32, f32::max);
+
+        let fits = block_width_mm <= available_stack_mm && longest_line_mm <= available_length_mm;
+        if fits || font_size <= min_font_size {
+            return (font_size, lines, widths);
+        }
+
+        font_size = (font_size - 1.0).max(min_font_size);
+    }
+}
+
+// Draw short tick marks at each internal column/row boundary, extending into the margin, plus
+// a small L-shaped mark at each page corner, so printed sheets can be stacked and cut
+// precisely. Marks sit at fixed page positions independent of `is_front`, so they stay aligned
+// with each other through the long-edge flip between front and back.
+fn draw_crop_marks(layer: &PdfLayerReference, layout: &Layout) {
+    let tick_length_mm = layout.margin_mm.min(3.0);
+    let corner_mark_mm = layout.margin_mm.min(5.0);
+
+    let draw_segment = |from: (f32, f32), to: (f32, f32)| {
+        layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(from.0), Mm(from.1)), false),
+                (Point::new(Mm(to.0), Mm(to.1)), false),
+            ],
+            is_closed: false,
+        });
+    };
+
+    // Vertical ticks at each internal column boundary, poking into the top and bottom margins
+    for col in 1..layout.cols {
+        let x = layout.margin_mm + col as f32 * layout.card_width_mm;
+        draw_segment((x, layout.page_height_mm), (x, layout.page_height_mm - tick_length_mm));
+        draw_segment((x, 0.0), (x, tick_length_mm));
+    }
+
+    // Horizontal ticks at each internal row boundary, poking into the left and right margins
+    for row in 1..layout.rows {
+        let y = layout.page_height_mm - layout.margin_mm - row as f32 * layout.card_height_mm;
+        draw_segment((0.0, y), (tick_length_mm, y));
+        draw_segment((layout.page_width_mm, y), (layout.page_width_mm - tick_length_mm, y));
+    }
+
+    // Corner marks: a small L at each of the four page corners
+    for &(corner_x, corner_y, dx, dy) in &[
+        (0.0, layout.page_height_mm, 1.0, -1.0),
+        (layout.page_width_mm, layout.page_height_mm, -1.0, -1.0),
+        (0.0, 0.0, 1.0, 1.0),
+        (layout.page_width_mm, 0.0, -1.0, 1.0),
+    ] {
+        draw_segment((corner_x, corner_y), (corner_x + dx * corner_mark_mm, corner_y));
+        draw_segment((corner_x, corner_y), (corner_x, corner_y + dy * corner_mark_mm));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn draw_card_grid(
     doc: &PdfDocumentReference,
     layer: PdfLayerIndex,
     page: PdfPageIndex,
     font: &IndirectFontRef,
+    regular_font: &IndirectFontRef,
+    metrics: &ResolvedMetrics,
     cards: &[Option<&FlashCard>],
     is_front: bool,
-    card_width_mm: f32,
-    card_height_mm: f32,
-    margin_mm: f32,
-) {
+    max_font_size: f32,
+    min_font_size: f32,
+    layout: &Layout,
+) -> Result<()> {
     let current_layer = doc.get_page(page).get_layer(layer);
+    let card_width_mm = layout.card_width_mm;
+    let card_height_mm = layout.card_height_mm;
+
+    draw_crop_marks(&current_layer, layout);
 
     for (idx, card_opt) in cards.iter().enumerate() {
         if let Some(card) = card_opt {
             // Calculate position in grid
             let (col, row) = if is_front {
                 // Front side: normal order (left to right, top to bottom)
-                (idx % GRID_COLS, idx / GRID_COLS)
+                (idx % layout.cols, idx / layout.cols)
             } else {
                 // Back side: horizontally mirrored for flip on long edge
                 // When you flip along the long edge (vertical axis), columns reverse
-                let original_col = idx % GRID_COLS;
-                let original_row = idx / GRID_COLS;
-                (GRID_COLS - 1 - original_col, original_row)
+                let original_col = idx % layout.cols;
+                let original_row = idx / layout.cols;
+                (layout.cols - 1 - original_col, original_row)
             };
 
             // Calculate position (origin is bottom-left in PDF)
-            let x = margin_mm + col as f32 * card_width_mm;
-            let y = A4_HEIGHT_MM - margin_mm - (row + 1) as f32 * card_height_mm;
+            let x = layout.margin_mm + col as f32 * card_width_mm;
+            let y = layout.page_height_mm - layout.margin_mm - (row + 1) as f32 * card_height_mm;
 
             // Draw card border
             let points = vec![
@@ -247,39 +780,379 @@ fn draw_card_grid(
 
             current_layer.add_line(line);
 
-            // Draw text rotated 90 degrees clockwise with word wrapping
-            let text = if is_front { &card.side_a } else { &card.side_b };
+            match card {
+                FlashCard::TwoSided { side_a, side_b } => {
+                    // Render the side's content: wrapped, auto-shrunk text rotated 90 degrees
+                    // clockwise, or a QR code / image centered in the card.
+                    let side = if is_front { side_a } else { side_b };
+
+                    match side {
+                        CardSide::Text(text) => draw_text_side(
+                            &current_layer,
+                            font,
+                            metrics,
+                            text,
+                            max_font_size,
+                            min_font_size,
+                            x,
+                            y,
+                            card_width_mm,
+                            card_height_mm,
+                        ),
+                        CardSide::Qr(data) => draw_qr_side(
+                            &current_layer,
+                            data,
+                            x,
+                            y,
+                            card_width_mm,
+                            card_height_mm,
+                            TEXT_MARGIN_MM,
+                        )?,
+                        CardSide::Image(path) => draw_image_side(
+                            &current_layer,
+                            path,
+                            x,
+                            y,
+                            card_width_mm,
+                            card_height_mm,
+                            TEXT_MARGIN_MM,
+                        )?,
+                    }
+                }
+                FlashCard::Structured { title, body, category, footer } => draw_structured_side(
+                    &current_layer,
+                    font,
+                    regular_font,
+                    metrics,
+                    title.as_deref(),
+                    body.as_deref(),
+                    category.as_deref(),
+                    footer.as_deref(),
+                    x,
+                    y,
+                    card_width_mm,
+                    card_height_mm,
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
 
-            let font_size = 18.0;
-            let line_spacing_mm = 7.0; // Horizontal space between wrapped lines
+// Draw `text`, wrapped and auto-shrunk to fit, rotated 90 degrees clockwise and centered
+// in the card occupying (x, y)..(x + card_width_mm, y + card_height_mm).
+#[allow(clippy::too_many_arguments)]
+fn draw_text_side(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    metrics: &ResolvedMetrics,
+    text: &str,
+    max_font_size: f32,
+    min_font_size: f32,
+    x: f32,
+    y: f32,
+    card_width_mm: f32,
+    card_height_mm: f32,
+) {
+    // Available space for text (rotated, so card height is the line-length axis and
+    // card width is the axis the wrapped lines stack along)
+    let available_length = card_height_mm - 2.0 * TEXT_MARGIN_MM;
+    let available_stack = card_width_mm - 2.0 * TEXT_MARGIN_MM;
 
-            // Available space for text (rotated, so height becomes the constraint)
-            let available_height = card_height_mm - 2.0 * TEXT_MARGIN_MM;
+    // Shrink the font until the wrapped text fits both axes, or we hit the floor
+    let (font_size, lines, widths) = fit_text(
+        text,
+        max_font_size,
+        min_font_size,
+        available_length,
+        available_stack,
+        metrics,
+    );
+    let line_spacing_mm = font_size * 0.3528 * 1.1; // Horizontal space between wrapped lines
 
-            // Wrap text if needed
-            let lines = wrap_text(text, font_size, available_height);
+    // Starting position (centered horizontally and, per line, along its own length)
+    let text_x = x + card_width_mm / 2.0;
+    let stack_offset_mm = (lines.len() as f32 - 1.0) * line_spacing_mm / 2.0;
+    let length_top_mm = y + card_height_mm - TEXT_MARGIN_MM;
 
-            // Starting position (same as before, centered horizontally)
-            let text_x = x + card_width_mm / 2.0;
-            let text_y = y + card_height_mm - TEXT_MARGIN_MM;
+    // Draw each line, offset horizontally for rotation
+    for (i, (line, width)) in lines.iter().zip(widths.iter()).enumerate() {
+        let line_x = text_x + stack_offset_mm - i as f32 * line_spacing_mm;
+        let line_y = length_top_mm - (available_length - width) / 2.0;
 
-            // Draw each line, offset horizontally for rotation
-            for (i, line) in lines.iter().enumerate() {
-                let line_x = text_x - i as f32 * line_spacing_mm;
+        layer.begin_text_section();
+        layer.set_font(font, font_size);
+        layer.set_line_height(font_size);
 
-                current_layer.begin_text_section();
-                current_layer.set_font(font, font_size);
-                current_layer.set_line_height(font_size);
+        layer.set_text_matrix(TextMatrix::TranslateRotate(
+            Mm(line_x).into(),
+            Mm(line_y).into(),
+            -90.0,
+        ));
 
-                current_layer.set_text_matrix(TextMatrix::TranslateRotate(
-                    Mm(line_x).into(),
-                    Mm(text_y).into(),
-                    -90.0,
-                ));
+        layer.write_text(line, font);
+        layer.end_text_section();
+    }
+}
 
-                current_layer.write_text(line, font);
-                current_layer.end_text_section();
-            }
+// Draw a structured card upright (not rotated): a bold title at the top, a word-wrapped body
+// in a smaller regular weight below it, and a category/footer tag at the bottom. Title and
+// body are each run through `fit_text` against the space actually available to them, so a
+// long title wraps/shrinks instead of crossing the card border, and a long body shrinks
+// instead of spilling past the bottom of the card (and into the footer). Shrinking stops at
+// each one's minimum font size; text that still doesn't fit there is drawn anyway and overflows.
+#[allow(clippy::too_many_arguments)]
+fn draw_structured_side(
+    layer: &PdfLayerReference,
+    font: &IndirectFontRef,
+    regular_font: &IndirectFontRef,
+    metrics: &ResolvedMetrics,
+    title: Option<&str>,
+    body: Option<&str>,
+    category: Option<&str>,
+    footer: Option<&str>,
+    x: f32,
+    y: f32,
+    card_width_mm: f32,
+    card_height_mm: f32,
+) {
+    const TITLE_FONT_SIZE: f32 = 14.0;
+    const TITLE_MIN_FONT_SIZE: f32 = 8.0;
+    const BODY_FONT_SIZE: f32 = 10.0;
+    const BODY_MIN_FONT_SIZE: f32 = 6.0;
+    const FOOTER_FONT_SIZE: f32 = 8.0;
+    const LINE_SPACING_FACTOR: f32 = 1.1; // matches the spacing fit_text itself assumes
+
+    let content_width_mm = card_width_mm - 2.0 * TEXT_MARGIN_MM;
+    let content_height_mm = card_height_mm - 2.0 * TEXT_MARGIN_MM;
+    let text_x = x + TEXT_MARGIN_MM;
+    let mut cursor_y = y + card_height_mm - TEXT_MARGIN_MM;
+
+    // Title is fitted to the top half of the content area, leaving the rest for the body.
+    if let Some(title) = title {
+        let title_stack_mm = content_height_mm / 2.0;
+        let (font_size, lines, _) =
+            fit_text(title, TITLE_FONT_SIZE, TITLE_MIN_FONT_SIZE, content_width_mm, title_stack_mm, metrics);
+        let line_spacing_mm = font_size * 0.3528 * LINE_SPACING_FACTOR;
+        for line in &lines {
+            draw_upright_line(layer, font, line, text_x, cursor_y, font_size);
+            cursor_y -= line_spacing_mm;
+        }
+    }
+
+    let footer_height_mm = if category.is_some() || footer.is_some() {
+        FOOTER_FONT_SIZE * 0.3528 * LINE_SPACING_FACTOR
+    } else {
+        0.0
+    };
+
+    if let Some(body) = body {
+        let available_body_height_mm = (cursor_y - (y + TEXT_MARGIN_MM + footer_height_mm)).max(0.0);
+        let (font_size, lines, _) = fit_text(
+            body,
+            BODY_FONT_SIZE,
+            BODY_MIN_FONT_SIZE,
+            content_width_mm,
+            available_body_height_mm,
+            metrics,
+        );
+        let line_spacing_mm = font_size * 0.3528 * LINE_SPACING_FACTOR;
+        for line in &lines {
+            draw_upright_line(layer, regular_font, line, text_x, cursor_y, font_size);
+            cursor_y -= line_spacing_mm;
         }
     }
+
+    if category.is_some() || footer.is_some() {
+        let tag = [category, footer].into_iter().flatten().collect::<Vec<_>>().join(" \u{b7} ");
+        draw_upright_line(layer, regular_font, &tag, text_x, y + TEXT_MARGIN_MM, FOOTER_FONT_SIZE);
+    }
+}
+
+// Write a single line of upright (unrotated) text starting at (x, y).
+fn draw_upright_line(layer: &PdfLayerReference, font: &IndirectFontRef, text: &str, x: f32, y: f32, font_size: f32) {
+    layer.begin_text_section();
+    layer.set_font(font, font_size);
+    layer.set_line_height(font_size);
+    layer.set_text_cursor(Mm(x), Mm(y));
+    layer.write_text(text, font);
+    layer.end_text_section();
+}
+
+// Render `data` as a QR code and place it centered in the card.
+fn draw_qr_side(
+    layer: &PdfLayerReference,
+    data: &str,
+    x: f32,
+    y: f32,
+    card_width_mm: f32,
+    card_height_mm: f32,
+    margin_mm: f32,
+) -> Result<()> {
+    let code = qrcode::QrCode::new(data.as_bytes())
+        .with_context(|| format!("Failed to generate QR code for {data:?}"))?;
+    let bitmap = code.render::<image_crate::Luma<u8>>().build();
+
+    place_image(
+        layer,
+        &image_crate::DynamicImage::ImageLuma8(bitmap),
+        x,
+        y,
+        card_width_mm,
+        card_height_mm,
+        margin_mm,
+    );
+
+    Ok(())
+}
+
+// Load the image at `path` and place it centered in the card.
+fn draw_image_side(
+    layer: &PdfLayerReference,
+    path: &Path,
+    x: f32,
+    y: f32,
+    card_width_mm: f32,
+    card_height_mm: f32,
+    margin_mm: f32,
+) -> Result<()> {
+    let img =
+        image_crate::open(path).with_context(|| format!("Failed to load image: {}", path.display()))?;
+
+    place_image(layer, &img, x, y, card_width_mm, card_height_mm, margin_mm);
+
+    Ok(())
+}
+
+// Center `image` in the card, scaled (preserving aspect ratio) to fit within
+// card_width_mm/card_height_mm minus `margin_mm` on every side.
+fn place_image(
+    layer: &PdfLayerReference,
+    image: &image_crate::DynamicImage,
+    x: f32,
+    y: f32,
+    card_width_mm: f32,
+    card_height_mm: f32,
+    margin_mm: f32,
+) {
+    const DPI: f32 = 300.0;
+    let mm_per_px = 25.4 / DPI;
+
+    let natural_width_mm = image.width() as f32 * mm_per_px;
+    let natural_height_mm = image.height() as f32 * mm_per_px;
+
+    let fit_width_mm = card_width_mm - 2.0 * margin_mm;
+    let fit_height_mm = card_height_mm - 2.0 * margin_mm;
+    let scale = (fit_width_mm / natural_width_mm).min(fit_height_mm / natural_height_mm);
+
+    let pdf_image = Image::from_dynamic_image(image);
+    pdf_image.add_to_layer(
+        layer.clone(),
+        ImageTransform {
+            translate_x: Some(Mm(x + card_width_mm / 2.0 - natural_width_mm * scale / 2.0)),
+            translate_y: Some(Mm(y + card_height_mm / 2.0 - natural_height_mm * scale / 2.0)),
+            scale_x: Some(scale),
+            scale_y: Some(scale),
+            dpi: Some(DPI),
+            rotate: None,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(fields: &[&str]) -> csv::StringRecord {
+        csv::StringRecord::from(fields.to_vec())
+    }
+
+    #[test]
+    fn looks_like_header_accepts_all_known_column_names() {
+        assert!(looks_like_header(&header(&["title", "body", "category", "footer"])));
+        assert!(looks_like_header(&header(&["Title", "Body"])));
+    }
+
+    #[test]
+    fn looks_like_header_rejects_a_single_recognized_cell() {
+        // A two-sided data row whose first side happens to be a column name (e.g. a
+        // vocabulary pair like "category|categorie") must not be mistaken for a header.
+        assert!(!looks_like_header(&header(&["category", "categorie"])));
+        assert!(!looks_like_header(&header(&["title", "bonjour"])));
+    }
+
+    #[test]
+    fn looks_like_header_rejects_unrecognized_cells() {
+        assert!(!looks_like_header(&header(&["hello", "world"])));
+    }
+
+    #[test]
+    fn looks_like_header_ignores_empty_cells() {
+        assert!(looks_like_header(&header(&["title", "body", ""])));
+        assert!(!looks_like_header(&header(&["title", ""])));
+    }
+
+    #[test]
+    fn wrap_text_splits_on_word_boundaries_within_the_available_width() {
+        let metrics = ResolvedMetrics::Builtin;
+        let (lines, widths) = wrap_text("one two three four", 10.0, 25.0, &metrics);
+        assert!(lines.len() > 1, "expected text to wrap into multiple lines, got {lines:?}");
+        for width in widths {
+            assert!(width <= 25.0, "line width {width} exceeds available width");
+        }
+        assert_eq!(lines.join(" "), "one two three four");
+    }
+
+    #[test]
+    fn wrap_text_hard_breaks_a_word_wider_than_the_available_width() {
+        let metrics = ResolvedMetrics::Builtin;
+        let (lines, widths) = wrap_text("Supercalifragilisticexpialidocious", 10.0, 15.0, &metrics);
+        assert!(lines.len() > 1);
+        for width in widths {
+            assert!(width <= 15.0, "line width {width} exceeds available width");
+        }
+        assert_eq!(lines.concat(), "Supercalifragilisticexpialidocious");
+    }
+
+    #[test]
+    fn fit_text_shrinks_font_size_until_the_text_fits() {
+        let metrics = ResolvedMetrics::Builtin;
+        let long_text = "one two three four five six seven eight nine ten";
+        let (font_size, lines, widths) = fit_text(long_text, 18.0, 6.0, 20.0, 30.0, &metrics);
+
+        assert!(font_size < 18.0, "expected the font size to shrink below the max");
+        assert!(font_size >= 6.0);
+        for width in &widths {
+            assert!(*width <= 20.0);
+        }
+        let line_spacing_mm = font_size * 0.3528 * 1.1;
+        assert!(lines.len() as f32 * line_spacing_mm <= 30.0 + 1e-3);
+    }
+
+    #[test]
+    fn fit_text_falls_back_to_min_font_size_when_nothing_fits() {
+        let metrics = ResolvedMetrics::Builtin;
+        let (font_size, _, _) = fit_text("unbreakable", 18.0, 10.0, 1.0, 1.0, &metrics);
+        assert_eq!(font_size, 10.0);
+    }
+
+    #[test]
+    fn layout_new_rejects_zero_cols_or_rows() {
+        assert!(Layout::new(PageSize::A4, 0, 4, 5.0).is_err());
+        assert!(Layout::new(PageSize::A4, 4, 0, 5.0).is_err());
+    }
+
+    #[test]
+    fn layout_new_rejects_a_margin_that_leaves_no_room_for_cards() {
+        assert!(Layout::new(PageSize::Custom(20.0, 20.0), 4, 4, 15.0).is_err());
+    }
+
+    #[test]
+    fn layout_new_computes_card_dimensions_from_page_size_and_grid() {
+        let layout = Layout::new(PageSize::A4, 4, 4, 5.0).unwrap();
+        assert_eq!(layout.card_width_mm, (210.0 - 2.0 * 5.0) / 4.0);
+        assert_eq!(layout.card_height_mm, (297.0 - 2.0 * 5.0) / 4.0);
+        assert_eq!(layout.cards_per_page(), 16);
+    }
 }